@@ -0,0 +1,29 @@
+//! The `Endpoint` trait: Tide's representation of a request handler.
+
+use futures::future::BoxFuture;
+use std::future::Future;
+
+use crate::{middleware::Context, response::IntoResponse, Response};
+
+/// An HTTP request handler.
+///
+/// This trait is automatically implemented for `Fn(Context<State>) -> Fut`
+/// closures whose output implements [`IntoResponse`], which is how most
+/// endpoints are written in practice; implementing it directly is rarely
+/// necessary.
+pub trait Endpoint<State>: Send + Sync + 'static {
+    /// Invoke the endpoint, producing a response.
+    fn call(&self, cx: Context<State>) -> BoxFuture<'static, Response>;
+}
+
+impl<State, F, Fut> Endpoint<State> for F
+where
+    F: Fn(Context<State>) -> Fut + Send + Sync + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: IntoResponse,
+{
+    fn call(&self, cx: Context<State>) -> BoxFuture<'static, Response> {
+        let fut = (self)(cx);
+        Box::pin(async move { fut.await.into_response() })
+    }
+}
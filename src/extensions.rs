@@ -0,0 +1,49 @@
+//! A typed, per-request map for passing values computed by middleware down
+//! to endpoints.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A type-keyed bag of request-scoped values.
+///
+/// A fresh `Extensions` is created for every request by [`Service::respond`](crate::server::Service)
+/// and threaded through [`Context`](crate::Context). Middleware in the
+/// [`Next`](crate::middleware::Next) chain can insert values it has computed
+/// (an authenticated user, a request id, a parsed session, ...), and any
+/// endpoint further down the chain can read them back out by type.
+#[derive(Debug, Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Create an empty `Extensions` map.
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+
+    /// Insert a value, returning the previous value of the same type, if any.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|boxed| boxed.downcast::<T>().ok())
+            .map(|boxed| *boxed)
+    }
+
+    /// Get a reference to a value of type `T`, if one has been inserted.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|boxed| boxed.downcast_ref::<T>())
+    }
+
+    /// Get a mutable reference to a value of type `T`, if one has been
+    /// inserted.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|boxed| boxed.downcast_mut::<T>())
+    }
+}
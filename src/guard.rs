@@ -0,0 +1,96 @@
+//! Request guards, used to disambiguate multiple endpoints registered on the
+//! same path and HTTP method.
+
+use http_service::Body;
+
+/// A predicate evaluated against an incoming request to decide whether a
+/// particular endpoint should handle it.
+///
+/// Guards are attached to a route via [`Route::guard`](crate::router::Route::guard).
+/// When several endpoints are registered for the same path and method, the
+/// router picks the first one registered whose guards all return `true`. If
+/// every candidate's guards fail, the request falls through to the normal
+/// not-found/fallback handling, as though nothing had matched at all.
+pub trait Guard: Send + Sync + 'static {
+    /// Returns `true` if `req` satisfies this guard.
+    fn check(&self, req: &http::Request<Body>) -> bool;
+}
+
+impl<F> Guard for F
+where
+    F: Fn(&http::Request<Body>) -> bool + Send + Sync + 'static,
+{
+    fn check(&self, req: &http::Request<Body>) -> bool {
+        (self)(req)
+    }
+}
+
+/// A guard that matches when a header is present and equal to `value`.
+#[derive(Debug)]
+pub struct HeaderGuard {
+    name: http::header::HeaderName,
+    value: http::header::HeaderValue,
+}
+
+impl Guard for HeaderGuard {
+    fn check(&self, req: &http::Request<Body>) -> bool {
+        req.headers().get(&self.name) == Some(&self.value)
+    }
+}
+
+/// A guard that matches when the `Host` header is `host`, ignoring a
+/// trailing `:port` and letter case.
+#[derive(Debug)]
+pub struct HostGuard {
+    host: String,
+}
+
+impl Guard for HostGuard {
+    fn check(&self, req: &http::Request<Body>) -> bool {
+        let host_header = match req.headers().get(http::header::HOST).and_then(|v| v.to_str().ok()) {
+            Some(host_header) => host_header,
+            None => return false,
+        };
+        strip_port(host_header).eq_ignore_ascii_case(&self.host)
+    }
+}
+
+/// Strips a trailing `:port` from an authority, leaving IPv6 literals
+/// (`[::1]`) untouched since their brackets, not the last colon, delimit the
+/// host.
+fn strip_port(authority: &str) -> &str {
+    if let Some(bracket_end) = authority.strip_prefix('[').and_then(|rest| rest.find(']')) {
+        return &authority[..=bracket_end + 1];
+    }
+    match authority.rfind(':') {
+        Some(colon) => &authority[..colon],
+        None => authority,
+    }
+}
+
+/// Build a guard matching requests that carry the header `name` set to
+/// exactly `value`.
+///
+/// ```rust,no_run
+/// use tide::guard;
+///
+/// # let mut app = tide::new();
+/// app.at("/").get(|_| async move { "default" })
+///     .guard(guard::header("accept", "application/json"));
+/// ```
+pub fn header(name: &'static str, value: &'static str) -> HeaderGuard {
+    HeaderGuard {
+        name: http::header::HeaderName::from_static(name),
+        value: http::header::HeaderValue::from_static(value),
+    }
+}
+
+/// Build a guard matching requests whose `Host` header is `host`, ignoring a
+/// trailing `:port` and letter case. `host` may itself carry a `:port`,
+/// which is stripped so `guard::host("example.com")` and
+/// `guard::host("example.com:8080")` behave identically.
+pub fn host(host: &str) -> HostGuard {
+    HostGuard {
+        host: strip_port(host).to_owned(),
+    }
+}
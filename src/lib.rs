@@ -0,0 +1,25 @@
+//! Tide is a minimal and pragmatic Rust web application framework built for
+//! rapid development. See [`Server`] for an overview.
+
+#![deny(missing_debug_implementations)]
+
+pub mod guard;
+
+mod endpoint;
+mod extensions;
+mod middleware;
+mod response;
+mod router;
+mod server;
+
+pub use endpoint::Endpoint;
+pub use extensions::Extensions;
+pub use middleware::{Context, Middleware, Next};
+pub use response::{IntoResponse, Response};
+pub use router::{Params, Route};
+pub use server::{Server, Service};
+
+/// Create a new Tide server with no initial state.
+pub fn new() -> Server<()> {
+    Server::new()
+}
@@ -0,0 +1,110 @@
+//! Request/response middleware and the per-request `Context`.
+
+use futures::future::BoxFuture;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{endpoint::Endpoint, extensions::Extensions, router::Params, Response};
+
+/// Everything an endpoint or middleware needs to handle a single request:
+/// the incoming request itself, the shared application state, the params
+/// captured by the matched route, and a typed map of request-scoped
+/// extensions.
+#[allow(missing_debug_implementations)]
+pub struct Context<State> {
+    state: Arc<State>,
+    req: http_service::Request,
+    params: Params,
+    extensions: Extensions,
+}
+
+impl<State> Context<State> {
+    /// Build a new `Context` for a single request.
+    pub fn new(state: Arc<State>, req: http_service::Request, params: Params, extensions: Extensions) -> Self {
+        Self {
+            state,
+            req,
+            params,
+            extensions,
+        }
+    }
+
+    /// The application state.
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// The incoming HTTP request.
+    pub fn request(&self) -> &http_service::Request {
+        &self.req
+    }
+
+    /// Parse a path param captured by the matched route.
+    pub fn param<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.params.get(key).and_then(|value| value.parse().ok())
+    }
+
+    /// The request-scoped extension map.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// A mutable reference to the request-scoped extension map, for
+    /// middleware to insert values that downstream middleware or the
+    /// endpoint can read back out.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Get a reference to a value of type `T` previously inserted into the
+    /// request-scoped extension map.
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get::<T>()
+    }
+
+    /// Read the request body and deserialize it as JSON.
+    pub async fn body_json<T: serde::de::DeserializeOwned>(&mut self) -> Result<T, std::io::Error> {
+        let body = std::mem::replace(self.req.body_mut(), http_service::Body::empty());
+        let bytes = body.into_vec().await?;
+        serde_json::from_slice(&bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Application-level middleware.
+///
+/// Middleware wraps the request/response cycle: it can inspect or modify
+/// the `Context` before calling `next.run(cx)` to continue down the chain,
+/// and can inspect or modify the resulting response on the way back out.
+pub trait Middleware<State>: Send + Sync + 'static {
+    /// Process the request, calling `next` to continue the chain.
+    fn handle<'a>(&'a self, cx: Context<State>, next: Next<'a, State>) -> BoxFuture<'a, Response>;
+}
+
+/// The remaining middleware (and, ultimately, the endpoint) to run for a
+/// request.
+#[allow(missing_debug_implementations)]
+pub struct Next<'a, State> {
+    endpoint: Arc<dyn Endpoint<State>>,
+    next_middleware: &'a [Arc<dyn Middleware<State>>],
+}
+
+impl<'a, State: Send + Sync + 'static> Next<'a, State> {
+    /// Build a `Next` that will run `middleware` in order before finally
+    /// dispatching to `endpoint`.
+    pub fn new(endpoint: Arc<dyn Endpoint<State>>, middleware: &'a [Arc<dyn Middleware<State>>]) -> Self {
+        Self {
+            endpoint,
+            next_middleware: middleware,
+        }
+    }
+
+    /// Run the next piece of middleware, or the endpoint if none remain.
+    pub fn run(mut self, cx: Context<State>) -> BoxFuture<'a, Response> {
+        if let Some((current, rest)) = self.next_middleware.split_first() {
+            self.next_middleware = rest;
+            current.handle(cx, self)
+        } else {
+            self.endpoint.call(cx)
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Conversion of endpoint return values into HTTP responses.
+
+/// An HTTP response, ready to be sent back to the client.
+pub type Response = http_service::Response;
+
+/// Conversion into a [`Response`].
+///
+/// This is implemented for `Response` itself, and for a handful of common
+/// convenience types so that endpoints can return plain data instead of
+/// building a `Response` by hand.
+pub trait IntoResponse: Send + 'static {
+    /// Convert `self` into an HTTP response.
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> Response {
+        http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(http_service::Body::from(self.as_bytes().to_vec()))
+            .unwrap()
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Response {
+        http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(http_service::Body::from(self.into_bytes()))
+            .unwrap()
+    }
+}
+
+/// Build a `200 OK` JSON response from any `Serialize` value.
+pub fn json(value: impl serde::Serialize) -> Response {
+    let body = serde_json::to_vec(&value).unwrap();
+    http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(http_service::Body::from(body))
+        .unwrap()
+}
@@ -0,0 +1,426 @@
+//! Route registration and request-to-endpoint matching.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{endpoint::Endpoint, guard::Guard, middleware::Middleware};
+
+/// The path parameters captured by a matched route, e.g. `:user` in
+/// `/hello/:user`.
+#[derive(Clone, Debug, Default)]
+pub struct Params {
+    values: HashMap<String, String>,
+}
+
+impl Params {
+    fn insert(&mut self, name: String, value: String) {
+        self.values.insert(name, value);
+    }
+
+    /// Look up a captured parameter by name.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+fn parse_pattern(path: &str) -> Vec<Segment> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if let Some(name) = s.strip_prefix('*') {
+                Segment::Wildcard(name.to_owned())
+            } else if let Some(name) = s.strip_prefix(':') {
+                Segment::Param(name.to_owned())
+            } else {
+                Segment::Static(s.to_owned())
+            }
+        })
+        .collect()
+}
+
+/// Matches `pattern` against `path`, returning the captured params and a
+/// specificity score (higher wins) so that a concrete path is preferred over
+/// a wildcard one when more than one resource could match the same request.
+fn match_segments(pattern: &[Segment], path: &[&str]) -> Option<(Params, u32)> {
+    let mut params = Params::default();
+    let mut score = 0u32;
+    let mut path_index = 0;
+
+    for (pattern_index, segment) in pattern.iter().enumerate() {
+        match segment {
+            Segment::Wildcard(name) => {
+                if !name.is_empty() {
+                    params.insert(name.clone(), path[path_index..].join("/"));
+                }
+                let _ = pattern_index;
+                return Some((params, score));
+            }
+            Segment::Static(expected) => {
+                if path.get(path_index) != Some(&expected.as_str()) {
+                    return None;
+                }
+                score += 2;
+                path_index += 1;
+            }
+            Segment::Param(name) => {
+                let value = path.get(path_index)?;
+                if !name.is_empty() {
+                    params.insert(name.clone(), (*value).to_owned());
+                }
+                score += 1;
+                path_index += 1;
+            }
+        }
+    }
+
+    if path_index == path.len() {
+        Some((params, score))
+    } else {
+        None
+    }
+}
+
+fn join_paths(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    let path = path.trim_start_matches('/');
+    if path.is_empty() {
+        if prefix.is_empty() {
+            "/".to_owned()
+        } else {
+            prefix.to_owned()
+        }
+    } else if prefix.is_empty() {
+        format!("/{}", path)
+    } else {
+        format!("{}/{}", prefix, path)
+    }
+}
+
+struct Candidate<State> {
+    endpoint: Arc<dyn Endpoint<State>>,
+    guards: Vec<Arc<dyn Guard>>,
+}
+
+impl<State> Clone for Candidate<State> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoint: self.endpoint.clone(),
+            guards: self.guards.clone(),
+        }
+    }
+}
+
+struct Resource<State> {
+    pattern: Vec<Segment>,
+    endpoints: HashMap<http::Method, Vec<Candidate<State>>>,
+    middleware: Vec<Arc<dyn Middleware<State>>>,
+}
+
+/// A server's routing table: a map from registered paths to the endpoints,
+/// guards and middleware attached to them.
+#[allow(missing_debug_implementations)]
+pub struct Router<State> {
+    resources: HashMap<String, Resource<State>>,
+}
+
+impl<State: Send + Sync + 'static> Router<State> {
+    /// Create an empty `Router`.
+    pub fn new() -> Self {
+        Self {
+            resources: HashMap::new(),
+        }
+    }
+
+    /// Add a new route at the given `path`, relative to this router's root.
+    pub fn at<'a>(&'a mut self, path: &str) -> Route<'a, State> {
+        Route::new(self, path.to_owned())
+    }
+
+    pub(crate) fn add(
+        &mut self,
+        path: &str,
+        method: http::Method,
+        endpoint: Arc<dyn Endpoint<State>>,
+        middleware: Vec<Arc<dyn Middleware<State>>>,
+    ) {
+        let candidate = Candidate {
+            endpoint,
+            guards: Vec::new(),
+        };
+        match self.resources.entry(path.to_owned()) {
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().endpoints.entry(method).or_insert_with(Vec::new).push(candidate);
+            }
+            Entry::Vacant(entry) => {
+                let mut endpoints = HashMap::new();
+                endpoints.insert(method, vec![candidate]);
+                entry.insert(Resource {
+                    pattern: parse_pattern(path),
+                    endpoints,
+                    middleware,
+                });
+            }
+        }
+    }
+
+    pub(crate) fn add_guard(&mut self, path: &str, method: &http::Method, guard: Arc<dyn Guard>) {
+        let candidates = self
+            .resources
+            .get_mut(path)
+            .and_then(|resource| resource.endpoints.get_mut(method))
+            .expect("Route::guard must follow a method combinator registered on the same route");
+        candidates
+            .last_mut()
+            .expect("Route::guard must follow a method combinator registered on the same route")
+            .guards
+            .push(guard);
+    }
+
+    /// Merge routes nested under `prefix` (via [`Route::nest`]) into this
+    /// router, prepending `scope_middleware` to whatever middleware each
+    /// nested route already carries.
+    ///
+    /// Unlike [`Router::merge`], this never panics on an overlapping path:
+    /// nesting different HTTP methods onto the same final path across
+    /// several `nest` calls is the normal way to build up a resource.
+    pub(crate) fn merge_nested(
+        &mut self,
+        prefix: &str,
+        nested: Router<State>,
+        scope_middleware: Vec<Arc<dyn Middleware<State>>>,
+    ) {
+        for (path, resource) in nested.resources {
+            let full_path = join_paths(prefix, &path);
+            match self.resources.entry(full_path.clone()) {
+                Entry::Occupied(mut entry) => {
+                    for (method, candidates) in resource.endpoints {
+                        entry
+                            .get_mut()
+                            .endpoints
+                            .entry(method)
+                            .or_insert_with(Vec::new)
+                            .extend(candidates);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    let mut middleware = scope_middleware.clone();
+                    middleware.extend(resource.middleware);
+                    entry.insert(Resource {
+                        pattern: parse_pattern(&full_path),
+                        endpoints: resource.endpoints,
+                        middleware,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Copy every route in `other` into `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` has a route registered at a path that already
+    /// exists on `self`, naming the conflicting path.
+    pub fn merge(&mut self, other: Router<State>) {
+        for (path, resource) in other.resources {
+            match self.resources.entry(path) {
+                Entry::Occupied(entry) => panic!(
+                    "cannot merge Servers: route `{}` is registered on both Servers",
+                    entry.key()
+                ),
+                Entry::Vacant(entry) => {
+                    entry.insert(resource);
+                }
+            }
+        }
+    }
+
+    /// Find the resource matching `path`, if any, and collect everything
+    /// needed to resolve it to a concrete endpoint for `method`.
+    pub fn route(&self, path: &str, method: http::Method) -> Selection<State> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        let mut best: Option<(&Resource<State>, Params, u32)> = None;
+        for resource in self.resources.values() {
+            if let Some((params, score)) = match_segments(&resource.pattern, &segments) {
+                let is_better = match &best {
+                    Some((_, _, best_score)) => score > *best_score,
+                    None => true,
+                };
+                if is_better {
+                    best = Some((resource, params, score));
+                }
+            }
+        }
+
+        match best {
+            Some((resource, params, _)) => {
+                let candidates = resource.endpoints.get(&method).cloned().unwrap_or_default();
+                // When the method has candidates, a request whose guards all
+                // fail is treated as though nothing matched, so no method
+                // list is reported for it.
+                let allowed_methods = if candidates.is_empty() {
+                    Some(resource.endpoints.keys().cloned().collect())
+                } else {
+                    None
+                };
+                Selection {
+                    candidates,
+                    params,
+                    middleware: resource.middleware.clone(),
+                    allowed_methods,
+                }
+            }
+            None => Selection {
+                candidates: Vec::new(),
+                params: Params::default(),
+                middleware: Vec::new(),
+                allowed_methods: None,
+            },
+        }
+    }
+}
+
+/// The result of resolving a path and method against a [`Router`]: either a
+/// matched resource (possibly with several guarded candidate endpoints), or
+/// nothing at all.
+#[allow(missing_debug_implementations)]
+pub struct Selection<State> {
+    candidates: Vec<Candidate<State>>,
+    params: Params,
+    middleware: Vec<Arc<dyn Middleware<State>>>,
+    allowed_methods: Option<Vec<http::Method>>,
+}
+
+impl<State: Send + Sync + 'static> Selection<State> {
+    /// Pick the first candidate endpoint whose guards all pass for `req`,
+    /// along with the route's captured params and scoped middleware stack.
+    pub fn select(
+        &self,
+        req: &http::Request<http_service::Body>,
+    ) -> Option<(Arc<dyn Endpoint<State>>, Params, Vec<Arc<dyn Middleware<State>>>)> {
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.guards.iter().all(|guard| guard.check(req)))
+            .map(|candidate| (candidate.endpoint.clone(), self.params.clone(), self.middleware.clone()))
+    }
+
+    /// The methods that do have at least one endpoint registered on the
+    /// matched resource, or `None` if no resource matched the path at all or
+    /// the matched resource has endpoints for the request's method.
+    pub fn allowed_methods(&self) -> Option<Vec<http::Method>> {
+        self.allowed_methods.clone()
+    }
+}
+
+/// A single registered route, returned by [`Server::at`](crate::Server::at).
+#[allow(missing_debug_implementations)]
+pub struct Route<'a, State> {
+    router: &'a mut Router<State>,
+    path: String,
+    middleware: Vec<Arc<dyn Middleware<State>>>,
+    last_method: Option<http::Method>,
+}
+
+impl<'a, State: Send + Sync + 'static> Route<'a, State> {
+    pub(crate) fn new(router: &'a mut Router<State>, path: String) -> Self {
+        Self {
+            router,
+            path,
+            middleware: Vec::new(),
+            last_method: None,
+        }
+    }
+
+    fn method(&mut self, method: http::Method, ep: impl Endpoint<State>) -> &mut Self {
+        self.router.add(&self.path, method.clone(), Arc::new(ep), self.middleware.clone());
+        self.last_method = Some(method);
+        self
+    }
+
+    /// Add an endpoint for `GET` requests.
+    pub fn get(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        self.method(http::Method::GET, ep)
+    }
+
+    /// Add an endpoint for `POST` requests.
+    pub fn post(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        self.method(http::Method::POST, ep)
+    }
+
+    /// Add an endpoint for `PUT` requests.
+    pub fn put(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        self.method(http::Method::PUT, ep)
+    }
+
+    /// Add an endpoint for `DELETE` requests.
+    pub fn delete(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        self.method(http::Method::DELETE, ep)
+    }
+
+    /// Add an endpoint for `HEAD` requests.
+    pub fn head(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        self.method(http::Method::HEAD, ep)
+    }
+
+    /// Add an endpoint for `PATCH` requests.
+    pub fn patch(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        self.method(http::Method::PATCH, ep)
+    }
+
+    /// Add an endpoint for `OPTIONS` requests.
+    pub fn options(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        self.method(http::Method::OPTIONS, ep)
+    }
+
+    /// Restrict the endpoint most recently registered on this route (e.g.
+    /// via [`Route::get`]) to requests for which `g` passes.
+    ///
+    /// When several endpoints are registered for the same path and method,
+    /// each behind its own `guard`, the router dispatches to the first one,
+    /// in registration order, whose guard passes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called before any method combinator has been called on this
+    /// route.
+    pub fn guard(&mut self, g: impl Guard) -> &mut Self {
+        let method = self
+            .last_method
+            .clone()
+            .expect("Route::guard must follow a method combinator such as Route::get");
+        self.router.add_guard(&self.path, &method, Arc::new(g));
+        self
+    }
+
+    /// Add middleware scoped to this route and anything nested under it.
+    ///
+    /// Scoped middleware runs after the request has been routed, between
+    /// the server's global middleware and the endpoint:
+    /// `[global middleware..] + [scoped middleware..] + endpoint`.
+    pub fn middleware(&mut self, m: impl Middleware<State>) -> &mut Self {
+        self.middleware.push(Arc::new(m));
+        self
+    }
+
+    /// Nest a group of routes under this route's path.
+    ///
+    /// `builder` receives a fresh `Router` to register routes on as though
+    /// it were the application root; those routes are then merged under
+    /// this route's path, inheriting any middleware already added via
+    /// [`Route::middleware`].
+    pub fn nest(&mut self, builder: impl FnOnce(&mut Router<State>)) -> &mut Self {
+        let mut nested = Router::new();
+        builder(&mut nested);
+        self.router.merge_nested(&self.path, nested, self.middleware.clone());
+        self
+    }
+}
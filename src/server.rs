@@ -1,10 +1,12 @@
 //! An HTTP Server.
 
 use futures::future::{self, BoxFuture};
-use http_service::HttpService;
+use http_service::{Body, HttpService};
 use std::sync::Arc;
 
 use crate::{
+    endpoint::Endpoint,
+    extensions::Extensions,
     middleware::{Middleware, Next, Context},
     router::{Route, Router},
 };
@@ -133,6 +135,8 @@ pub struct Server<State> {
     router: Router<State>,
     middleware: Vec<Arc<dyn Middleware<State>>>,
     state: State,
+    fallback: Arc<dyn Endpoint<State>>,
+    fallback_is_default: bool,
 }
 
 impl Server<()> {
@@ -155,6 +159,8 @@ impl<State: Send + Sync + 'static> Server<State> {
             router: Router::new(),
             middleware: Vec::new(),
             state,
+            fallback: Arc::new(NotFoundEndpoint),
+            fallback_is_default: true,
         }
     }
 
@@ -226,6 +232,50 @@ impl<State: Send + Sync + 'static> Server<State> {
         self
     }
 
+    /// Set the endpoint to dispatch to when no route matches the request.
+    ///
+    /// By default a plain `404 Not Found` response is served. Use this to
+    /// serve a custom not-found page, or to fall back to e.g. a bundled
+    /// single-page application's `index.html`.
+    ///
+    /// ```rust,no_run
+    /// # let mut app = tide::new();
+    /// app.fallback(|_| async move { "these are not the endpoints you are looking for" });
+    /// ```
+    pub fn fallback(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        self.fallback = Arc::new(ep);
+        self.fallback_is_default = false;
+        self
+    }
+
+    /// Combine the routes and middleware of another `Server` into this one.
+    ///
+    /// This makes it practical to split a large application into
+    /// independently constructed `Server`s that share the same `State`, and
+    /// assemble them into a single app in `main`. `other`'s middleware is
+    /// appended after `self`'s, running in the order the two apps were
+    /// merged.
+    ///
+    /// If `self` has not set a custom [`Server::fallback`], `other`'s
+    /// fallback (if any) is adopted; otherwise `self`'s fallback is kept.
+    /// The first fallback configured, in merge order, wins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` has a route registered at a concrete path that is
+    /// already registered on `self`, naming the conflicting path. Tide
+    /// prefers an early, loud failure here over silently shadowing one of
+    /// the two routes.
+    pub fn merge(&mut self, other: Server<State>) -> &mut Self {
+        self.router.merge(other.router);
+        self.middleware.extend(other.middleware);
+        if self.fallback_is_default && !other.fallback_is_default {
+            self.fallback = other.fallback;
+            self.fallback_is_default = false;
+        }
+        self
+    }
+
     /// Make this app into an `HttpService`.
     ///
     /// This lower-level method lets you host a Tide application within an HTTP
@@ -235,16 +285,51 @@ impl<State: Send + Sync + 'static> Server<State> {
             router: Arc::new(self.router),
             state: Arc::new(self.state),
             middleware: Arc::new(self.middleware),
+            fallback: self.fallback,
         }
     }
 
     /// Asynchronously serve the app at the given address.
+    ///
+    /// Runs until the process is killed. To drain in-flight requests and stop
+    /// cleanly on a signal, use [`Server::bind_with_graceful_shutdown`]
+    /// instead.
     #[cfg(feature = "hyper")]
     pub async fn bind(self, addr: impl std::net::ToSocketAddrs) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.bind_with_graceful_shutdown(addr, future::pending()).await
+    }
+
+    /// Asynchronously serve the app at the given address, stopping cleanly
+    /// once `shutdown` resolves.
+    ///
+    /// Once `shutdown` resolves, the listener stops accepting new
+    /// connections; the call then returns only after all in-flight
+    /// `respond` futures have completed. This lets deployments drain
+    /// requests on `SIGTERM` instead of dropping them mid-flight.
+    ///
+    /// ```rust, no_run
+    /// # #![feature(async_await)]
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    /// let app = tide::new();
+    /// app.bind_with_graceful_shutdown("127.0.0.1:8000", async move {
+    ///     // resolve this future on SIGTERM, a shutdown channel, etc.
+    /// }).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "hyper")]
+    pub async fn bind_with_graceful_shutdown(
+        self,
+        addr: impl std::net::ToSocketAddrs,
+        shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         let mut listener = runtime::net::TcpListener::bind(addr)?;
         let server = http_service_hyper::Server::builder(listener.incoming())
             .with_spawner(runtime::task::Spawner::new());
-        server.serve(self.into_service()).await?;
+        server
+            .serve(self.into_service())
+            .with_graceful_shutdown(shutdown)
+            .await?;
         Ok(())
     }
 }
@@ -261,6 +346,7 @@ pub struct Service<State> {
     router: Arc<Router<State>>,
     state: Arc<State>,
     middleware: Arc<Vec<Arc<dyn Middleware<State>>>>,
+    fallback: Arc<dyn Endpoint<State>>,
 }
 
 impl<State: Sync + Send + 'static> HttpService for Service<State> {
@@ -278,13 +364,46 @@ impl<State: Sync + Send + 'static> HttpService for Service<State> {
         let router = self.router.clone();
         let middleware = self.middleware.clone();
         let state = self.state.clone();
+        let fallback = self.fallback.clone();
 
         Box::pin(async move {
+            let mut chain = (*middleware).clone();
             let fut = {
-                let (endpoint, params) = router.route(&path, method).into_components();
-                let cx = Context::new(state, params);
-                let next = Next::new(endpoint, &middleware);
-                next.run(req, cx)
+                let route = router.route(&path, method.clone());
+                let (endpoint, params, scoped_middleware): (Arc<dyn Endpoint<State>>, _, _) =
+                    match route.select(&req) {
+                        Some((endpoint, params, scoped_middleware)) => {
+                            (endpoint, params, scoped_middleware)
+                        }
+                        None => match route.allowed_methods() {
+                            Some(allowed) if method == http::Method::OPTIONS => (
+                                Arc::new(OptionsEndpoint {
+                                    allowed: with_standard_methods(allowed),
+                                }),
+                                Default::default(),
+                                Vec::new(),
+                            ),
+                            Some(allowed) => (
+                                Arc::new(MethodNotAllowedEndpoint {
+                                    allowed: with_standard_methods(allowed),
+                                }),
+                                Default::default(),
+                                Vec::new(),
+                            ),
+                            None => (fallback, Default::default(), Vec::new()),
+                        },
+                    };
+
+                // The chain run for a request is the server's global
+                // middleware followed by whatever middleware was attached to
+                // the matched route or one of its enclosing scopes, in
+                // registration order, so that `[global..] + [scoped..] +
+                // endpoint` is assembled only once the route is known.
+                chain.extend(scoped_middleware);
+
+                let cx = Context::new(state, req, params, Extensions::new());
+                let next = Next::new(endpoint, &chain);
+                next.run(cx)
             };
 
             Ok(fut.await)
@@ -292,29 +411,97 @@ impl<State: Sync + Send + 'static> HttpService for Service<State> {
     }
 }
 
+/// The endpoint used as a `Server`'s fallback until overridden via
+/// [`Server::fallback`].
+struct NotFoundEndpoint;
+
+impl<State: Send + Sync + 'static> Endpoint<State> for NotFoundEndpoint {
+    fn call(&self, _cx: Context<State>) -> BoxFuture<'static, crate::Response> {
+        Box::pin(async move {
+            http::Response::builder()
+                .status(http::StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .unwrap()
+        })
+    }
+}
+
+/// Served when a resource is matched but has no endpoint for the request's
+/// method, listing the methods that *are* available in the `Allow` header.
+struct MethodNotAllowedEndpoint {
+    allowed: Vec<http::Method>,
+}
+
+impl<State: Send + Sync + 'static> Endpoint<State> for MethodNotAllowedEndpoint {
+    fn call(&self, _cx: Context<State>) -> BoxFuture<'static, crate::Response> {
+        let allow = allow_header_value(&self.allowed);
+        Box::pin(async move {
+            http::Response::builder()
+                .status(http::StatusCode::METHOD_NOT_ALLOWED)
+                .header(http::header::ALLOW, allow)
+                .body(Body::empty())
+                .unwrap()
+        })
+    }
+}
+
+/// Automatically answers `OPTIONS` for a matched resource that has no
+/// explicit `OPTIONS` endpoint registered.
+struct OptionsEndpoint {
+    allowed: Vec<http::Method>,
+}
+
+impl<State: Send + Sync + 'static> Endpoint<State> for OptionsEndpoint {
+    fn call(&self, _cx: Context<State>) -> BoxFuture<'static, crate::Response> {
+        let allow = allow_header_value(&self.allowed);
+        Box::pin(async move {
+            http::Response::builder()
+                .status(http::StatusCode::NO_CONTENT)
+                .header(http::header::ALLOW, allow)
+                .body(Body::empty())
+                .unwrap()
+        })
+    }
+}
+
+fn allow_header_value(methods: &[http::Method]) -> String {
+    methods
+        .iter()
+        .map(http::Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Extends a resource's registered methods with `OPTIONS`, so the `Allow`
+/// header on a `405`/auto-`OPTIONS` response reflects every method the
+/// resource actually responds to: `OPTIONS` is always auto-handled here
+/// unless the user registered an explicit endpoint for it, in which case
+/// `route.select` would already have matched that endpoint directly.
+///
+/// This deliberately does not add `HEAD`: Tide does not implicitly route
+/// `HEAD` requests to a `GET` endpoint, so advertising it here would be
+/// misleading.
+fn with_standard_methods(mut allowed: Vec<http::Method>) -> Vec<http::Method> {
+    if !allowed.contains(&http::Method::OPTIONS) {
+        allowed.push(http::Method::OPTIONS);
+    }
+    allowed
+}
+
 #[cfg(test)]
 mod tests {
     use futures::executor::block_on;
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
     use super::*;
-    use crate::{middleware::Next, Context, Response};
+    use crate::{guard, middleware::Next, Context, Response};
 
     fn simulate_request<'a, State: Default + Clone + Send + Sync + 'static>(
         app: &'a Server<State>,
         path: &'a str,
         method: http::Method,
     ) -> BoxFuture<'a, Response> {
-        let (endpoint, params) = app.router.route(path, method.clone()).into_components();
-
-        let state = Arc::new(State::default());
-        let req = http::Request::builder()
-            .method(method)
-            .body(http_service::Body::empty())
-            .unwrap();
-        let cx = Context::new(state, req, params);
-        let next = Next::new(endpoint, &app.middleware);
-        next.run(cx)
+        dispatch(app, path, method)
     }
 
     #[test]
@@ -387,4 +574,231 @@ mod tests {
             assert_eq!(&*body, format!("{} {}", path, method).as_bytes());
         }
     }
-}
\ No newline at end of file
+
+    /// Dispatches a request through `Router::route` and `Selection::select`,
+    /// falling back to the 405, auto-`OPTIONS` and fallback endpoints as
+    /// `Service::respond` does. Only the server's global middleware is run;
+    /// route-scoped middleware is not.
+    fn dispatch<'a, State: Default + Send + Sync + 'static>(
+        app: &'a Server<State>,
+        path: &'a str,
+        method: http::Method,
+    ) -> BoxFuture<'a, Response> {
+        let req = http::Request::builder()
+            .method(method.clone())
+            .body(http_service::Body::empty())
+            .unwrap();
+        let route = app.router.route(path, method.clone());
+        let (endpoint, params, _middleware): (Arc<dyn Endpoint<State>>, _, _) = match route.select(&req) {
+            Some(selected) => selected,
+            None => match route.allowed_methods() {
+                Some(allowed) if method == http::Method::OPTIONS => (
+                    Arc::new(OptionsEndpoint {
+                        allowed: with_standard_methods(allowed),
+                    }),
+                    Default::default(),
+                    Vec::new(),
+                ),
+                Some(allowed) => (
+                    Arc::new(MethodNotAllowedEndpoint {
+                        allowed: with_standard_methods(allowed),
+                    }),
+                    Default::default(),
+                    Vec::new(),
+                ),
+                None => (app.fallback.clone(), Default::default(), Vec::new()),
+            },
+        };
+        let cx = Context::new(Arc::new(State::default()), req, params, Extensions::new());
+        let next = Next::new(endpoint, &app.middleware);
+        next.run(cx)
+    }
+
+    #[test]
+    fn merge_combines_routes_from_both_servers() {
+        let mut a = Server::new();
+        a.at("/a").get(|_| async move { "a" });
+
+        let mut b = Server::new();
+        b.at("/b").get(|_| async move { "b" });
+
+        a.merge(b);
+
+        for (path, expected) in &[("/a", "a"), ("/b", "b")] {
+            let res = block_on(dispatch(&a, path, http::Method::GET));
+            let body = block_on(res.into_body().into_vec()).unwrap();
+            assert_eq!(&*body, expected.as_bytes());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "/a")]
+    fn merge_panics_on_overlapping_concrete_path() {
+        let mut a = Server::new();
+        a.at("/a").get(|_| async move { "a" });
+
+        let mut b = Server::new();
+        b.at("/a").get(|_| async move { "a again" });
+
+        a.merge(b);
+    }
+
+    #[test]
+    fn default_fallback_is_404() {
+        let app: Server<()> = Server::new();
+        let res = block_on(dispatch(&app, "/unknown", http::Method::GET));
+        assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn custom_fallback_is_used_on_miss() {
+        let mut app = Server::new();
+        app.fallback(|_| async move { "custom fallback" });
+        app.at("/known").get(|_| async move { "known" });
+
+        let res = block_on(dispatch(&app, "/unknown", http::Method::GET));
+        assert_eq!(res.status(), http::StatusCode::OK);
+        let body = block_on(res.into_body().into_vec()).unwrap();
+        assert_eq!(&*body, b"custom fallback");
+    }
+
+    #[test]
+    fn guard_disambiguates_endpoints_on_the_same_path() {
+        let mut app = Server::new();
+        app.at("/")
+            .get(|_| async move { "b" })
+            .guard(guard::host("b.example.com"));
+        app.at("/")
+            .get(|_| async move { "a" })
+            .guard(guard::host("a.example.com"));
+
+        for (host, expected) in &[("a.example.com", "a"), ("b.example.com:8080", "b")] {
+            let req = http::Request::builder()
+                .method(http::Method::GET)
+                .header(http::header::HOST, *host)
+                .body(http_service::Body::empty())
+                .unwrap();
+            let route = app.router.route("/", http::Method::GET);
+            let (endpoint, params, _middleware) = route.select(&req).expect("a guard should match");
+            let cx = Context::new(Arc::new(()), req, params, Extensions::new());
+            let res = block_on(endpoint.call(cx));
+            let body = block_on(res.into_body().into_vec()).unwrap();
+            assert_eq!(&*body, expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn guard_miss_falls_through_to_not_found() {
+        let mut app = Server::new();
+        app.at("/").get(|_| async move { "a" }).guard(guard::host("a.example.com"));
+
+        let res = block_on(dispatch(&app, "/", http::Method::GET));
+        assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn unsupported_method_returns_405_with_allow_header() {
+        let mut app = Server::new();
+        app.at("/a").get(|_| async move { "a" });
+
+        let res = block_on(dispatch(&app, "/a", http::Method::POST));
+        assert_eq!(res.status(), http::StatusCode::METHOD_NOT_ALLOWED);
+        let allow = res
+            .headers()
+            .get(http::header::ALLOW)
+            .and_then(|v| v.to_str().ok())
+            .unwrap();
+        assert!(allow.contains("GET"));
+        assert!(allow.contains("OPTIONS"));
+    }
+
+    #[test]
+    fn options_is_auto_handled_when_unregistered() {
+        let mut app = Server::new();
+        app.at("/a").get(|_| async move { "a" });
+
+        let res = block_on(dispatch(&app, "/a", http::Method::OPTIONS));
+        assert_eq!(res.status(), http::StatusCode::NO_CONTENT);
+        let allow = res
+            .headers()
+            .get(http::header::ALLOW)
+            .and_then(|v| v.to_str().ok())
+            .unwrap();
+        assert!(allow.contains("GET"));
+    }
+
+    #[test]
+    fn scoped_middleware_runs_between_global_middleware_and_endpoint() {
+        type Log = Arc<Mutex<Vec<&'static str>>>;
+
+        struct RecordingMiddleware(&'static str);
+
+        impl Middleware<Log> for RecordingMiddleware {
+            fn handle<'a>(&'a self, cx: Context<Log>, next: Next<'a, Log>) -> BoxFuture<'a, Response> {
+                cx.state().lock().unwrap().push(self.0);
+                next.run(cx)
+            }
+        }
+
+        let log: Log = Arc::new(Mutex::new(Vec::new()));
+        let mut app = Server::with_state(log.clone());
+        app.middleware(RecordingMiddleware("global"));
+        app.at("/admin")
+            .middleware(RecordingMiddleware("scoped"))
+            .get(|cx: Context<Log>| async move {
+                cx.state().lock().unwrap().push("endpoint");
+                "ok"
+            });
+
+        let req = http::Request::builder()
+            .method(http::Method::GET)
+            .body(http_service::Body::empty())
+            .unwrap();
+        let route = app.router.route("/admin", http::Method::GET);
+        let (endpoint, params, scoped_middleware) = route.select(&req).expect("should match");
+        let mut chain = app.middleware.clone();
+        chain.extend(scoped_middleware);
+        let cx = Context::new(Arc::new(app.state.clone()), req, params, Extensions::new());
+        let next = Next::new(endpoint, &chain);
+        block_on(next.run(cx));
+
+        assert_eq!(&*log.lock().unwrap(), &["global", "scoped", "endpoint"]);
+    }
+
+    #[test]
+    fn extensions_round_trip_from_middleware_to_endpoint() {
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        struct RequestId(u32);
+
+        struct InjectRequestId;
+
+        impl Middleware<()> for InjectRequestId {
+            fn handle<'a>(&'a self, mut cx: Context<()>, next: Next<'a, ()>) -> BoxFuture<'a, Response> {
+                cx.extensions_mut().insert(RequestId(42));
+                next.run(cx)
+            }
+        }
+
+        let mut app = Server::new();
+        app.middleware(InjectRequestId);
+        app.at("/").get(|cx: Context<()>| async move {
+            let id = cx.extension::<RequestId>().copied().unwrap();
+            id.0.to_string()
+        });
+
+        let res = block_on(dispatch(&app, "/", http::Method::GET));
+        let body = block_on(res.into_body().into_vec()).unwrap();
+        assert_eq!(&*body, b"42");
+    }
+
+    #[test]
+    #[cfg(feature = "hyper")]
+    fn graceful_shutdown_returns_once_signaled() {
+        let app: Server<()> = Server::new();
+
+        // `future::ready` resolves immediately, so the listener should stop
+        // accepting connections and `bind_with_graceful_shutdown` should
+        // return right away instead of serving forever.
+        block_on(app.bind_with_graceful_shutdown("127.0.0.1:0", future::ready(()))).unwrap();
+    }
+}